@@ -1,11 +1,106 @@
 use atomic_float::AtomicF32;
 use egui::{Color32, Visuals};
 use nih_plug::prelude::*;
+use nih_plug::util;
 use nih_plug_egui::{create_egui_editor, egui, widgets, EguiState};
+use std::f32::consts::FRAC_PI_2;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+/// The time it takes for a held peak to decay by 12 dB (to a quarter of its value) once the
+/// signal falls silent.
+const PEAK_METER_DECAY_MS: f32 = 300.0;
+
+/// MIDI CC #10 is the standard General MIDI "Pan" controller, 0-127 with 64 as the center. It's
+/// treated as an absolute position.
+const CC_PAN: u8 = 10;
+/// The standard MIDI (N)RPN "Data Increment"/"Data Decrement" controllers, reused here to drive a
+/// relative pan nudge. These are distinct CC numbers from `CC_PAN` so absolute and relative input
+/// never have to be disambiguated from the same value.
+const CC_PAN_NUDGE_RIGHT: u8 = 96;
+const CC_PAN_NUDGE_LEFT: u8 = 97;
+/// How far one nudge increment/decrement moves the MIDI pan offset.
+const PAN_NUDGE_STEP: f32 = 0.05;
+/// How long the MIDI-driven pan offset takes to glide to a new target.
+const MIDI_PAN_SMOOTHING_MS: f32 = 10.0;
+
 pub struct Pan {
     params: Arc<PanParams>,
+
+    /// The left and right output peak meters, in linear gain. These are read by the editor to
+    /// draw the level bars and are not persisted as they aren't parameters.
+    left_peak_meter: Arc<AtomicF32>,
+    right_peak_meter: Arc<AtomicF32>,
+    /// The per-sample decay multiplier for the peak meters, recomputed in `initialize()` from the
+    /// sample rate.
+    peak_meter_decay: f32,
+
+    /// A pan offset driven by MIDI CC #10 and the nudge controllers, added on top of the `pan`
+    /// parameter. This can't write to `pan` directly since `ProcessContext` has no audio-thread
+    /// parameter setter, so it's tracked here and smoothed independently instead.
+    midi_pan: Smoother<f32>,
+    /// The last target handed to `midi_pan`, kept around so relative nudges accumulate from the
+    /// current position rather than from whatever the smoother has glided to so far.
+    midi_pan_target: f32,
+    sample_rate: f32,
+}
+
+/// The curve used to derive left/right gains from the `pan` parameter. The linear law is cheap
+/// but dips to -6 dB in the center for correlated material, so a few more natural-sounding
+/// options are offered alongside it.
+#[derive(Enum, Debug, PartialEq, Eq)]
+pub enum PanLaw {
+    #[id = "linear"]
+    #[name = "Linear (-6 dB)"]
+    Linear,
+    #[id = "constant-power-3db"]
+    #[name = "Constant Power (-3 dB)"]
+    ConstantPower3dB,
+    #[id = "compromise-4_5db"]
+    #[name = "Compromise (-4.5 dB)"]
+    CompromiseMinus4_5dB,
+    #[id = "minus-6db"]
+    #[name = "-6 dB"]
+    Minus6dB,
+}
+
+/// How the stereo field is decoded before the pan law's gains are applied.
+#[derive(Enum, Debug, PartialEq, Eq)]
+pub enum StereoMode {
+    /// The current behavior: each input channel is independently attenuated by the pan law.
+    #[id = "balance"]
+    #[name = "Balance"]
+    Balance,
+    /// Sums the input to mono first, then distributes it across the field, so a hard-panned
+    /// source collapses correctly instead of just being attenuated in place.
+    #[id = "pan"]
+    #[name = "Pan"]
+    Pan,
+    /// Treats the left channel as a mono base signal and the right channel as a per-sample pan
+    /// offset, turning the plugin into a creative stereo decoder.
+    #[id = "base-pan"]
+    #[name = "Base-Pan"]
+    BasePan,
+}
+
+impl PanLaw {
+    /// Computes the left and right gains for a pan value in `[-1, 1]` according to this law.
+    fn gains(&self, pan: f32) -> (f32, f32) {
+        let (linear_left, linear_right) = ((1.0 - pan) / 2.0, (1.0 + pan) / 2.0);
+
+        match self {
+            PanLaw::Linear | PanLaw::Minus6dB => (linear_left, linear_right),
+            PanLaw::ConstantPower3dB => {
+                let theta = (pan * 0.5 + 0.5) * FRAC_PI_2;
+                (theta.cos(), theta.sin())
+            }
+            PanLaw::CompromiseMinus4_5dB => {
+                let theta = (pan * 0.5 + 0.5) * FRAC_PI_2;
+                let (power_left, power_right) = (theta.cos(), theta.sin());
+                ((linear_left * power_left).sqrt(), (linear_right * power_right).sqrt())
+            }
+        }
+    }
 }
 
 #[derive(Params)]
@@ -17,12 +112,32 @@ pub struct PanParams {
 
     #[id = "pan"]
     pub pan: FloatParam,
+
+    #[id = "pan_law"]
+    pub pan_law: EnumParam<PanLaw>,
+
+    /// Stereo width applied to the mid/side decomposition before panning. `0.0` collapses to
+    /// mono, `1.0` leaves the input untouched, and values above `1.0` exaggerate the sides.
+    #[id = "width"]
+    pub width: FloatParam,
+
+    /// How the stereo field is decoded before the pan law is applied.
+    #[id = "mode"]
+    pub mode: EnumParam<StereoMode>,
 }
 
 impl Default for Pan {
     fn default() -> Self {
         Self {
             params: Arc::new(PanParams::default()),
+
+            left_peak_meter: Arc::new(AtomicF32::new(0.0)),
+            right_peak_meter: Arc::new(AtomicF32::new(0.0)),
+            peak_meter_decay: 1.0,
+
+            midi_pan: Smoother::new(SmoothingStyle::Linear(MIDI_PAN_SMOOTHING_MS)),
+            midi_pan_target: 0.0,
+            sample_rate: 1.0,
         }
     }
 }
@@ -40,7 +155,27 @@ impl Default for PanParams {
                     max: 1.0,  // Full right
                 },
             )
+            .with_smoother(SmoothingStyle::Linear(10.0))
             .with_string_to_value(formatters::s2v_f32_panning()),
+
+            // Defaults to the prior hard-coded law so existing projects and the out-of-the-box
+            // sound don't change; users can opt into constant power from here.
+            pan_law: EnumParam::new("Pan Law", PanLaw::Linear),
+
+            width: FloatParam::new(
+                "Width",
+                1.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 2.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(10.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            mode: EnumParam::new("Mode", StereoMode::Balance),
         }
     }
 }
@@ -68,6 +203,8 @@ impl Plugin for Pan {
 
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
 
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
+
     type SysExMessage = ();
     type BackgroundTask = ();
 
@@ -77,6 +214,8 @@ impl Plugin for Pan {
 
     fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         let params = self.params.clone();
+        let left_peak_meter = self.left_peak_meter.clone();
+        let right_peak_meter = self.right_peak_meter.clone();
         create_egui_editor(
             self.params.editor_state.clone(),
             (),
@@ -106,6 +245,23 @@ impl Plugin for Pan {
                         ui.add(
                             widgets::ParamSlider::for_param(&params.pan, setter).with_width(200.0),
                         );
+                        ui.add(
+                            widgets::ParamSlider::for_param(&params.pan_law, setter)
+                                .with_width(200.0),
+                        );
+                        ui.add(
+                            widgets::ParamSlider::for_param(&params.width, setter)
+                                .with_width(200.0),
+                        );
+                        ui.add(
+                            widgets::ParamSlider::for_param(&params.mode, setter)
+                                .with_width(200.0),
+                        );
+
+                        ui.horizontal(|ui| {
+                            draw_peak_meter(ui, left_peak_meter.load(Ordering::Relaxed));
+                            draw_peak_meter(ui, right_peak_meter.load(Ordering::Relaxed));
+                        });
                     });
                 });
             },
@@ -118,6 +274,14 @@ impl Plugin for Pan {
         buffer_config: &BufferConfig,
         _context: &mut impl InitContext<Self>,
     ) -> bool {
+        self.peak_meter_decay = 0.25f32.powf(
+            (buffer_config.sample_rate * PEAK_METER_DECAY_MS / 1000.0).recip(),
+        );
+
+        self.sample_rate = buffer_config.sample_rate;
+        self.midi_pan_target = 0.0;
+        self.midi_pan.reset(0.0);
+
         true
     }
 
@@ -125,25 +289,99 @@ impl Plugin for Pan {
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        for mut channel_samples in buffer.iter_samples() {
-            let pan = self.params.pan.smoothed.next();
-
-            // Calculate gains for left and right channels based on pan value
-            let left_gain = (1.0 - pan) / 2.0;
-            let right_gain = (1.0 + pan) / 2.0;
-
-            // Apply the pan by adjusting the gain for each sample
-            for (sample0, sample1) in channel_samples
-                .iter_mut()
-                .zip(channel_samples.iter_mut().skip(1))
-            {
-                *sample0 = *sample0 * left_gain; // Adjust gain for the left channel
-                *sample1 = *sample1 * right_gain; // Adjust gain for the right channel
+        let mut left_peak = self.left_peak_meter.load(Ordering::Relaxed);
+        let mut right_peak = self.right_peak_meter.load(Ordering::Relaxed);
+
+        let mut next_event = context.next_event();
+        for (sample_id, mut channel_samples) in buffer.iter_samples().enumerate() {
+            // Handle any MIDI events that fall at or before this sample so CC automation stays
+            // sample-accurate. `ProcessContext` has no audio-thread parameter setter, so these
+            // feed `midi_pan`, an internal offset that's added to the `pan` parameter below
+            // instead of trying to write to it directly.
+            while let Some(event) = next_event {
+                if event.timing() > sample_id as u32 {
+                    break;
+                }
+
+                if let NoteEvent::MidiCC { cc, value, .. } = event {
+                    if cc == CC_PAN {
+                        // CC #10 is absolute: the raw value maps directly onto the full pan
+                        // range, with 64 landing at (approximately) center.
+                        self.midi_pan_target = (value * 2.0 - 1.0).clamp(-1.0, 1.0);
+                        self.midi_pan.set_target(self.sample_rate, self.midi_pan_target);
+                    } else if cc == CC_PAN_NUDGE_RIGHT {
+                        self.midi_pan_target =
+                            (self.midi_pan_target + PAN_NUDGE_STEP).clamp(-1.0, 1.0);
+                        self.midi_pan.set_target(self.sample_rate, self.midi_pan_target);
+                    } else if cc == CC_PAN_NUDGE_LEFT {
+                        self.midi_pan_target =
+                            (self.midi_pan_target - PAN_NUDGE_STEP).clamp(-1.0, 1.0);
+                        self.midi_pan.set_target(self.sample_rate, self.midi_pan_target);
+                    }
+                }
+
+                next_event = context.next_event();
+            }
+
+            let pan = (self.params.pan.smoothed.next() + self.midi_pan.next()).clamp(-1.0, 1.0);
+            let width = self.params.width.smoothed.next();
+
+            // Mid/side width stage, applied before panning. Only meaningful on stereo layouts,
+            // and skipped in `BasePan` mode where the right channel is a pan-offset signal
+            // rather than a balance channel, so it must reach the decode below untouched.
+            if channel_samples.len() == 2 && self.params.mode.value() != StereoMode::BasePan {
+                let left = *channel_samples.get_mut(0).unwrap();
+                let right = *channel_samples.get_mut(1).unwrap();
+
+                let mid = (left + right) * 0.5;
+                let side = (left - right) * 0.5 * width;
+
+                *channel_samples.get_mut(0).unwrap() = mid + side;
+                *channel_samples.get_mut(1).unwrap() = mid - side;
+            }
+
+            // Calculate gains for left and right channels based on pan value and the selected law
+            let pan_law = self.params.pan_law.value();
+            let (left_gain, right_gain) = pan_law.gains(pan);
+
+            // Apply the pan according to the selected stereo decode mode
+            if channel_samples.len() == 2 {
+                let left_in = *channel_samples.get_mut(0).unwrap();
+                let right_in = *channel_samples.get_mut(1).unwrap();
+
+                let (left_out, right_out) = match self.params.mode.value() {
+                    StereoMode::Balance => (left_in * left_gain, right_in * right_gain),
+                    StereoMode::Pan => {
+                        let mono = (left_in + right_in) * 0.5;
+                        (mono * left_gain, mono * right_gain)
+                    }
+                    StereoMode::BasePan => {
+                        let base = left_in;
+                        let modulation = right_in.clamp(-1.0, 1.0);
+                        let (base_left_gain, base_right_gain) =
+                            pan_law.gains((pan + modulation).clamp(-1.0, 1.0));
+                        (base * base_left_gain, base * base_right_gain)
+                    }
+                };
+
+                *channel_samples.get_mut(0).unwrap() = left_out;
+                *channel_samples.get_mut(1).unwrap() = right_out;
+            }
+
+            if channel_samples.len() == 2 {
+                let left_out = *channel_samples.get_mut(0).unwrap();
+                let right_out = *channel_samples.get_mut(1).unwrap();
+
+                left_peak = left_out.abs().max(left_peak * self.peak_meter_decay);
+                right_peak = right_out.abs().max(right_peak * self.peak_meter_decay);
             }
         }
 
+        self.left_peak_meter.store(left_peak, Ordering::Relaxed);
+        self.right_peak_meter.store(right_peak, Ordering::Relaxed);
+
         ProcessStatus::Normal
     }
 }
@@ -169,3 +407,26 @@ impl Vst3Plugin for Pan {
 
 nih_export_clap!(Pan);
 nih_export_vst3!(Pan);
+
+/// Draws a small vertical level meter bar for `peak`, a linear gain value, next to the pan
+/// controls so users can see the gain redistribution the pan law is applying.
+fn draw_peak_meter(ui: &mut egui::Ui, peak: f32) {
+    const METER_HEIGHT: f32 = 80.0;
+    const METER_WIDTH: f32 = 10.0;
+    const MIN_DB: f32 = -60.0;
+
+    let peak_db = util::gain_to_db(peak);
+    let fraction = ((peak_db - MIN_DB) / -MIN_DB).clamp(0.0, 1.0);
+
+    let (rect, _response) =
+        ui.allocate_exact_size(egui::vec2(METER_WIDTH, METER_HEIGHT), egui::Sense::hover());
+    ui.painter().rect_filled(rect, 2.0, Color32::from_gray(30));
+
+    let filled_height = rect.height() * fraction;
+    let filled_rect = egui::Rect::from_min_max(
+        egui::pos2(rect.min.x, rect.max.y - filled_height),
+        rect.max,
+    );
+    ui.painter()
+        .rect_filled(filled_rect, 2.0, Color32::from_rgb(0, 166, 251));
+}